@@ -1,16 +1,21 @@
 use core::time::Duration;
+use std::ops::Bound;
 
+use actix_web::get;
 use actix_web::post;
 use actix_web::web;
 use actix_web::web::Data;
+use actix_web::HttpResponse;
 use actix_web::Responder;
+use futures::stream;
 use openraft::error::CheckIsLeaderError;
-use openraft::error::Fatal;
 use openraft::error::Infallible;
 use openraft::error::RaftError;
 use openraft::BasicNode;
-use openraft::RaftState;
-use tokio::sync::oneshot;
+use openraft::RaftMetrics;
+use openraft::ServerState;
+use serde::Deserialize;
+use serde::Serialize;
 use web::Json;
 
 use crate::app::App;
@@ -44,59 +49,245 @@ pub async fn read(app: Data<App>, req: Json<String>) -> actix_web::Result<impl R
 
 #[post("/consistent_read")]
 pub async fn consistent_read(app: Data<App>, req: Json<String>) -> actix_web::Result<impl Responder> {
-    let ret = app.raft.is_leader().await;
-
-    match ret {
-        Ok(_) => {
-            // Retrieve the `read_index` at which we are allowed to serve the read.
-            // Note that the index we receive might be higher than it was at the time of the `is_leader`
-            // request, but this is no issue, as a higher read index is still linearizable.
-            // TODO: this extra call can be avoided by having `is_leader` return the `read_index` immediately,
-            // but that requires an API change.
-            let (tx, rx) = oneshot::channel();
-            let fn_read_index = |raft_state: &RaftState<_, _, _>| {
-                let _ = tx.send(raft_state.committed.map(|log_id| log_id.index));
-            };
-            app.raft.external_request(fn_read_index);
-            let res = rx.await;
-            let read_index = match res {
-                Ok(x) => x, //Note that `x` should never be `None`
-                Err(recv_err) => {
-                    tracing::error!("error awaiting raft core: {}", recv_err);
-                    let res: Result<String, RaftError<NodeId, CheckIsLeaderError<NodeId, BasicNode>>> =
-                        Err(RaftError::Fatal(Fatal::Stopped)); //TODO: can this only be caused by a shutdown?
-                    return Ok(Json(res));
-                }
-            };
-
-            // Wait until `read_index` is applied; we can see this by subscribing to `RaftMetrics`
-            // 3 seconds is the client-side timeout as well
-            let res = app
-                .raft
-                .wait(Some(Duration::from_millis(3_000)))
-                .log_at_least(read_index, "apply logs until read_index")
-                .await;
-            match res {
-                Ok(_) => (), //No need for metrics
-                Err(wait_err) =>
-                //TODO: create more general `ReadError` type for timeout case
-                {
-                    tracing::error!("error awaiting metrics for `read_index`: {}", wait_err);
-                    let res: Result<String, RaftError<NodeId, CheckIsLeaderError<NodeId, BasicNode>>> =
-                        Err(RaftError::Fatal(Fatal::Stopped));
-                    return Ok(Json(res));
-                }
-            };
-
-            // Now we can safely read the value in the state machine
-            let state_machine = app.store.state_machine.read().await;
-            let key = req.0;
-            let value = state_machine.data.get(&key).cloned();
-
-            let res: Result<String, RaftError<NodeId, CheckIsLeaderError<NodeId, BasicNode>>> =
-                Ok(value.unwrap_or_default());
-            Ok(Json(res))
+    let read_index = match read_index(&app).await {
+        Ok(read_index) => read_index,
+        Err(e) => return Ok(read_error_response(e)),
+    };
+
+    match serve_read_at(&app, read_index, req.0).await {
+        Ok(value) => Ok(HttpResponse::Ok().json(Ok::<_, RaftError<NodeId, CheckIsLeaderError<NodeId, BasicNode>>>(value))),
+        Err(e) => Ok(read_error_response(e)),
+    }
+}
+
+/// Confirm quorum leadership via `ensure_linearizable` and return the committed log index a
+/// subsequent read must wait for. This replaces the old `is_leader` + `external_request` dance,
+/// which required a second round-trip into `RaftCore` to read `committed` and could race with a
+/// concurrent commit.
+///
+/// Assumes `Raft::ensure_linearizable` returns `Result<Option<LogId<NodeId>>, RaftError<NodeId,
+/// CheckIsLeaderError<NodeId, Node>>>` — `Ok(None)` meaning quorum is confirmed but nothing has
+/// committed yet. `scan` depends on the same signature. Check this against the `openraft` version
+/// actually pinned in `Cargo.toml` before merging; the core crate isn't part of this checkout, so
+/// it can't be verified here.
+async fn read_index(app: &App) -> Result<u64, ServeReadError> {
+    match app.raft.ensure_linearizable().await {
+        Ok(Some(read_log_id)) => Ok(read_log_id.index),
+        Ok(None) => Ok(0),
+        Err(e) => Err(ServeReadError::Raft(e)),
+    }
+}
+
+/// Either a `RaftError` from `ensure_linearizable` itself, or this node failing to catch up to
+/// the confirmed read index before the deadline.
+///
+/// `RaftError::Fatal(Fatal::Stopped)` used to be (mis)used for both: a `wait` timeout just means
+/// the node is reachable but lagging, not that `RaftCore` has shut down, and reporting it that
+/// way tells callers to stop retrying when they should back off and retry instead. The proper
+/// fix is a dedicated `ReadError` enum in `openraft::error` (with its own `Timeout` variant) that
+/// callers can match on directly; until that lands upstream, this local enum keeps the two cases
+/// apart at the handler boundary instead of collapsing them.
+enum ServeReadError {
+    Raft(RaftError<NodeId, CheckIsLeaderError<NodeId, BasicNode>>),
+    Timeout(Duration),
+}
+
+fn read_error_response(err: ServeReadError) -> HttpResponse {
+    match err {
+        ServeReadError::Raft(e) => HttpResponse::Ok().json(Err::<(), _>(e)),
+        ServeReadError::Timeout(timeout) => {
+            tracing::warn!("timed out after {:?} waiting for the state machine to catch up to the read index", timeout);
+            HttpResponse::ServiceUnavailable().json(format!(
+                "timed out after {:?} waiting for the state machine to catch up; the node is reachable but lagging, retry later",
+                timeout
+            ))
         }
-        Err(e) => Ok(Json(Err(e))),
+    }
+}
+
+/// Wait for `read_index` to be applied.
+async fn wait_for_index(app: &App, read_index: u64) -> Result<(), ServeReadError> {
+    // 3 seconds is the client-side timeout as well
+    let wait_timeout = Duration::from_millis(3_000);
+    let res = app
+        .raft
+        .wait(Some(wait_timeout))
+        .log_at_least(read_index, "apply logs until read_index")
+        .await;
+    if let Err(wait_err) = res {
+        tracing::error!("error awaiting metrics for `read_index`: {}", wait_err);
+        return Err(ServeReadError::Timeout(wait_timeout));
+    }
+    Ok(())
+}
+
+/// Wait for `read_index` to be applied, then serve `key` from the state machine.
+async fn serve_read_at(app: &App, read_index: u64, key: String) -> Result<String, ServeReadError> {
+    wait_for_index(app, read_index).await?;
+    Ok(serve_read(app, key).await)
+}
+
+/// Read `key` from the state machine as-is, without waiting for any particular index.
+async fn serve_read(app: &App, key: String) -> String {
+    let state_machine = app.store.state_machine.read().await;
+    state_machine.data.get(&key).cloned().unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanRequest {
+    /// Only keys starting with this prefix are returned. Empty means "no filter".
+    #[serde(default)]
+    pub prefix: String,
+    /// Maximum number of key/value pairs to return.
+    #[serde(default = "ScanRequest::default_limit")]
+    pub limit: usize,
+}
+
+impl ScanRequest {
+    fn default_limit() -> usize {
+        1000
+    }
+}
+
+/// Iterate the state machine for keys starting with `prefix`, streaming matches back one at a
+/// time as newline-delimited JSON.
+///
+/// The matches are taken from a single read-locked pass over `data` (at most `limit` of them,
+/// cloned out while the lock is held), so the whole response reflects one atomic snapshot at or
+/// beyond the confirmed read index, the same linearizability guarantee `consistent_read` gives a
+/// single key. Re-acquiring the lock between chunks would let a write commit mid-scan and break
+/// that guarantee, so memory use is bounded by `limit` rather than by the full keyspace.
+#[post("/scan")]
+pub async fn scan(app: Data<App>, req: Json<ScanRequest>) -> actix_web::Result<impl Responder> {
+    let read_index = match read_index(&app).await {
+        Ok(read_index) => read_index,
+        Err(e) => return Ok(read_error_response(e)),
+    };
+    if let Err(e) = wait_for_index(&app, read_index).await {
+        return Ok(read_error_response(e));
+    }
+
+    let ScanRequest { prefix, limit } = req.0;
+    let matches = {
+        let state_machine = app.store.state_machine.read().await;
+        state_machine
+            .data
+            .range((Bound::Included(prefix.clone()), Bound::Unbounded))
+            .take_while(|(k, _)| k.starts_with(&prefix))
+            .take(limit)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let body = stream::iter(matches.into_iter().map(|entry| {
+        let mut line = serde_json::to_vec(&entry).expect("key/value pair is always serializable");
+        line.push(b'\n');
+        Ok::<_, actix_web::Error>(web::Bytes::from(line))
+    }));
+
+    Ok(HttpResponse::Ok().content_type("application/x-ndjson").streaming(body))
+}
+
+/// A node whose last-applied index trails its committed index by more than this many entries
+/// does not count as caught up.
+const MAX_REPLICATION_LAG: u64 = 1_000;
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub ok: bool,
+}
+
+/// Liveness: if this handler runs at all, the process is up. Unlike `/status`, this never
+/// reflects cluster state, so it must not be used to decide whether to route reads here.
+#[get("/health")]
+pub async fn health() -> impl Responder {
+    Json(HealthResponse { ok: true })
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplicationStatus {
+    pub node_id: NodeId,
+    pub matched_index: Option<u64>,
+    pub lag: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusResponse {
+    pub term: u64,
+    pub leader_id: Option<NodeId>,
+    pub role: String,
+    pub last_applied: Option<u64>,
+    pub committed: Option<u64>,
+    pub replication: Vec<ReplicationStatus>,
+    pub snapshot: Option<u64>,
+    /// Whether this node is caught up enough to serve consistent reads.
+    pub ready_for_read: bool,
+}
+
+/// Readiness/diagnostic status derived from `RaftMetrics`: term, leader, role, last-applied vs.
+/// committed index, per-follower replication lag and snapshot state. Complements the
+/// `wait().log_at_least(...)` logic already used in `consistent_read`: load balancers can poll
+/// this to route reads only to nodes that are not isolated and not lagging too far behind.
+///
+/// Responds `503 Service Unavailable` when there is no known leader or when this node's
+/// last-applied index trails its committed index by more than `MAX_REPLICATION_LAG`, so it
+/// doubles as a readiness probe. Per-follower lag is reported but does not affect readiness.
+#[get("/status")]
+pub async fn status(app: Data<App>) -> actix_web::Result<impl Responder> {
+    let metrics: RaftMetrics<NodeId, BasicNode> = app.raft.metrics().borrow().clone();
+
+    let committed = metrics.committed.map(|log_id| log_id.index);
+    let last_applied = metrics.last_applied.map(|log_id| log_id.index);
+
+    // `replication` is keyed by every node `RaftMetrics` tracks a match index for, which can
+    // include this node itself; filter it out so "replication" only ever lists followers.
+    let local_id = metrics.id;
+    let replication: Vec<ReplicationStatus> = metrics
+        .replication
+        .as_ref()
+        .map(|matched_by_node| {
+            matched_by_node
+                .iter()
+                .filter(|(node_id, _)| **node_id != local_id)
+                .map(|(node_id, matched)| {
+                    let matched_index = matched.as_ref().map(|log_id| log_id.index);
+                    let lag = committed.zip(matched_index).map(|(c, m)| c.saturating_sub(m));
+                    ReplicationStatus { node_id: *node_id, matched_index, lag }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Readiness is about this node: how far its state machine trails the index it knows committed.
+    let own_lag = committed.unwrap_or(0).saturating_sub(last_applied.unwrap_or(0));
+    let ready_for_read = metrics.current_leader.is_some() && own_lag <= MAX_REPLICATION_LAG;
+
+    let res = StatusResponse {
+        term: metrics.current_term,
+        leader_id: metrics.current_leader,
+        role: role_str(&metrics.state).to_string(),
+        last_applied,
+        committed,
+        replication,
+        snapshot: metrics.snapshot.map(|log_id| log_id.index),
+        ready_for_read,
+    };
+
+    if ready_for_read {
+        Ok(HttpResponse::Ok().json(res))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(res))
+    }
+}
+
+/// Explicit mapping instead of `format!("{:?}", state)`, which would leak `ServerState`'s
+/// `Debug` representation as part of this API's wire format.
+fn role_str(state: &ServerState) -> &'static str {
+    match state {
+        ServerState::Leader => "leader",
+        ServerState::Candidate => "candidate",
+        ServerState::Follower => "follower",
+        ServerState::Learner => "learner",
+        ServerState::Shutdown => "shutdown",
     }
 }